@@ -1,6 +1,9 @@
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, ExitStatus, Stdio};
-use std::thread::sleep;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep, JoinHandle};
 use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
@@ -9,7 +12,7 @@ use serde_json::Value;
 use ureq::Agent;
 use ureq::AgentBuilder;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about = "Bridge Codex to a local Ollama runtime", long_about = None)]
 struct Cli {
     /// Path to the Codex executable (defaults to `codex` on $PATH)
@@ -40,6 +43,12 @@ struct Cli {
     #[arg(long, default_value = "ollama")]
     api_key: String,
 
+    /// Bearer token for remote or secured Ollama endpoints, sent as
+    /// `Authorization: Bearer <token>` on every request Mover makes to
+    /// Ollama, and forwarded to Codex so it authenticates against `/v1` too
+    #[arg(long, env = "OLLAMA_API_KEY")]
+    ollama_api_key: Option<String>,
+
     /// Seconds to wait for `ollama serve` to become available
     #[arg(long, default_value_t = 45)]
     readiness_timeout: u64,
@@ -56,14 +65,50 @@ struct Cli {
     #[arg(long)]
     serve_only: bool,
 
+    /// Print the models already installed in Ollama and exit
+    #[arg(long)]
+    list_models: bool,
+
     /// Prompt used when warming the Ollama model
     #[arg(long, default_value = "Codex warm-up ping.")]
     warm_prompt: String,
+
+    /// How long Ollama should keep the model loaded after a request, e.g.
+    /// `5m`, `1h`, or `-1` to never unload it
+    #[arg(long, default_value = "5m", allow_hyphen_values = true)]
+    keep_alive: String,
+
+    /// Context window size to request from the model, in tokens
+    #[arg(long, default_value_t = 4096)]
+    num_ctx: u32,
+
+    /// Additional generation option as `key=value` (repeatable), merged
+    /// into the Ollama `options` object; values are parsed as JSON when
+    /// possible, otherwise treated as strings
+    #[arg(long = "option", value_name = "KEY=VALUE")]
+    option: Vec<String>,
 }
 
+/// How often `serve_only` mode re-issues a preload request to keep the
+/// model resident across multiple Codex invocations.
+const PRELOAD_REFRESH_INTERVAL: Duration = Duration::from_secs(240);
+
+/// How often the watchdog polls Ollama for reachability.
+const WATCHDOG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Initial and maximum delay between consecutive restart attempts once
+/// Ollama is found unreachable, doubling on each failure.
+const WATCHDOG_RESTART_BACKOFF: Duration = Duration::from_secs(2);
+const WATCHDOG_MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Restart attempts the watchdog makes in one round before giving up on that
+/// round; the next poll that finds Ollama unreachable starts a new round.
+const WATCHDOG_MAX_RESTART_ATTEMPTS: u32 = 5;
+
 struct OllamaSupervisor {
     host: String,
     port: u16,
+    api_key: Option<String>,
     started_here: bool,
     child: Option<Child>,
 }
@@ -73,6 +118,7 @@ impl OllamaSupervisor {
         let mut supervisor = Self {
             host: cli.host.clone(),
             port: cli.port,
+            api_key: cli.ollama_api_key.clone(),
             started_here: false,
             child: None,
         };
@@ -89,18 +135,45 @@ impl OllamaSupervisor {
             "[mover] starting `{} serve` bound to {}:{}",
             cli.ollama_bin, cli.host, cli.port
         );
+        supervisor.respawn(cli)?;
+        Ok(supervisor)
+    }
+
+    /// Spawns a fresh `ollama serve` and waits for it to become reachable,
+    /// replacing any previously tracked child. Used both for the initial
+    /// launch and by the watchdog when it detects an outage.
+    /// Returns `true` if a new `ollama serve` was spawned, `false` if one
+    /// was already reachable and nothing was done.
+    fn respawn(&mut self, cli: &Cli) -> Result<bool> {
+        // Mirror `ensure_running`: if a server is already reachable (ours or
+        // someone else's), leave it alone and leave `started_here` as-is
+        // rather than spawning a duplicate and claiming ownership of it.
+        if self.is_reachable() {
+            return Ok(false);
+        }
+
+        // We're about to replace `self.child`; make sure we don't leak the
+        // process we previously tracked (it may just be hung, not dead).
+        if let Some(mut child) = self.child.take() {
+            if let Err(err) = child.kill() {
+                eprintln!("[mover] failed to terminate previous `ollama serve`: {err}");
+            }
+            let _ = child.wait();
+        }
+
         let mut cmd = Command::new(&cli.ollama_bin);
         cmd.arg("serve")
             .env("OLLAMA_HOST", &cli.host)
             .env("OLLAMA_PORT", cli.port.to_string())
+            .env("OLLAMA_CONTEXT_LENGTH", cli.num_ctx.to_string())
             .stdin(Stdio::null())
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit());
         let child = cmd.spawn().context("failed to spawn `ollama serve`")?;
-        supervisor.child = Some(child);
-        supervisor.started_here = true;
-        supervisor.wait_until_ready(Duration::from_secs(cli.readiness_timeout))?;
-        Ok(supervisor)
+        self.child = Some(child);
+        self.started_here = true;
+        self.wait_until_ready(Duration::from_secs(cli.readiness_timeout))?;
+        Ok(true)
     }
 
     fn wait_until_ready(&mut self, timeout: Duration) -> Result<()> {
@@ -134,7 +207,8 @@ impl OllamaSupervisor {
     fn is_reachable(&self) -> bool {
         let url = format!("http://{}:{}/api/tags", self.host, self.port);
         let agent = build_agent(Duration::from_secs(2), Duration::from_secs(2));
-        match agent.get(&url).call() {
+        let request = with_auth(agent.get(&url), self.api_key.as_deref());
+        match request.call() {
             Ok(resp) => resp.status() < 500,
             Err(_) => false,
         }
@@ -154,23 +228,193 @@ impl Drop for OllamaSupervisor {
     }
 }
 
+/// Watches `OllamaSupervisor` in the background and respawns `ollama serve`
+/// if it stops answering, so a mid-session crash doesn't silently fail every
+/// subsequent request.
+struct Watchdog {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Spawns the watchdog thread. When `refresh_preload` is set it also
+    /// periodically re-issues the preload request, which is what keeps a
+    /// `serve_only` server warm across multiple Codex invocations.
+    fn spawn(cli: Cli, supervisor: Arc<Mutex<OllamaSupervisor>>, refresh_preload: bool) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut last_preload = Instant::now();
+            while !thread_stop.load(Ordering::Relaxed) {
+                sleep(WATCHDOG_POLL_INTERVAL);
+                if thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let reachable = supervisor.lock().unwrap().is_reachable();
+                if !reachable {
+                    eprintln!("[mover] watchdog: Ollama is unreachable, attempting to restart");
+                    restart_with_backoff(&cli, &supervisor);
+                    last_preload = Instant::now();
+                } else if refresh_preload && last_preload.elapsed() >= PRELOAD_REFRESH_INTERVAL {
+                    if let Err(err) = preload_model(&cli) {
+                        eprintln!("[mover] watchdog: failed to refresh preload: {err}");
+                    }
+                    last_preload = Instant::now();
+                }
+            }
+        });
+
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Signals the watchdog thread to exit and waits for it to finish.
+    fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Blocks until the watchdog thread exits on its own (it never does,
+    /// short of a panic), used to keep `serve_only` mode running forever.
+    fn join(mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn restart_with_backoff(cli: &Cli, supervisor: &Arc<Mutex<OllamaSupervisor>>) {
+    let mut backoff = WATCHDOG_RESTART_BACKOFF;
+    for attempt in 1..=WATCHDOG_MAX_RESTART_ATTEMPTS {
+        match supervisor.lock().unwrap().respawn(cli) {
+            Ok(spawned) => {
+                if spawned {
+                    eprintln!("[mover] watchdog: restarted `ollama serve`");
+                } else {
+                    eprintln!("[mover] watchdog: Ollama is reachable again, no restart needed");
+                }
+                if let Err(err) = preload_model(cli) {
+                    eprintln!("[mover] watchdog: preload after restart failed: {err}");
+                }
+                return;
+            }
+            Err(err) => {
+                eprintln!("[mover] watchdog: restart attempt {attempt} failed: {err}");
+                if attempt == WATCHDOG_MAX_RESTART_ATTEMPTS {
+                    eprintln!(
+                        "[mover] watchdog: giving up after {attempt} restart attempts"
+                    );
+                    return;
+                }
+                sleep(backoff);
+                backoff = (backoff * 2).min(WATCHDOG_MAX_RESTART_BACKOFF);
+            }
+        }
+    }
+}
+
+fn fetch_installed_models(cli: &Cli) -> Result<Vec<String>> {
+    let url = format!("http://{}:{}/api/tags", cli.host, cli.port);
+    let agent = build_agent(Duration::from_secs(5), Duration::from_secs(10));
+    let request = with_auth(agent.get(&url), cli.ollama_api_key.as_deref());
+    let value: Value = request
+        .call()
+        .context("failed to query `/api/tags` on Ollama")?
+        .into_json()
+        .context("failed to decode `/api/tags` response from Ollama")?;
+
+    let models = value
+        .get("models")
+        .and_then(Value::as_array)
+        .context("`/api/tags` response did not contain a `models` array")?;
+
+    Ok(models
+        .iter()
+        .filter_map(|model| model.get("name").and_then(Value::as_str))
+        .map(String::from)
+        .collect())
+}
+
 fn ensure_model_available(cli: &Cli) -> Result<()> {
-    if cli.skip_pull {
+    let installed = fetch_installed_models(cli)?;
+    if installed.iter().any(|name| name == &cli.model) {
+        println!("[mover] model {} is already installed", cli.model);
         return Ok(());
     }
 
-    println!("[mover] pulling model {}", cli.model);
-    let status = Command::new(&cli.ollama_bin)
-        .arg("pull")
-        .arg(&cli.model)
-        .status()
-        .context("failed to run `ollama pull`")?;
-    if !status.success() {
+    if cli.skip_pull {
         bail!(
-            "`ollama pull` exited with status {}",
-            format_exit_status(status)
+            "model `{}` is not installed and `--skip-pull` was set; installed models: {}",
+            cli.model,
+            installed.join(", ")
         );
     }
+
+    pull_model(cli)
+}
+
+fn pull_model(cli: &Cli) -> Result<()> {
+    println!("[mover] pulling model {}", cli.model);
+    let url = format!("http://{}:{}/api/pull", cli.host, cli.port);
+    let body = serde_json::json!({
+        "model": cli.model,
+        "stream": true,
+    });
+    let agent = build_agent(Duration::from_secs(5), Duration::from_secs(600));
+    let request = with_auth(agent.post(&url), cli.ollama_api_key.as_deref());
+    let response = match request.send_json(body) {
+        Ok(resp) => resp,
+        Err(ureq::Error::Status(code, resp)) => {
+            let text = resp.into_string().unwrap_or_default();
+            bail!("`/api/pull` request failed with HTTP {code}: {text}");
+        }
+        Err(err) => bail!("failed to send `/api/pull` request to Ollama: {err}"),
+    };
+
+    let mut reader = BufReader::new(response.into_reader());
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let bytes_read = reader
+            .read_line(&mut line)
+            .context("failed to read `/api/pull` progress stream")?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let event: Value = serde_json::from_str(trimmed)
+            .with_context(|| format!("failed to parse `/api/pull` progress line: {trimmed}"))?;
+        if let Some(error) = event.get("error") {
+            bail!("Ollama pull error: {error}");
+        }
+
+        let status = event.get("status").and_then(Value::as_str).unwrap_or("");
+        let total = event.get("total").and_then(Value::as_u64);
+        let completed = event.get("completed").and_then(Value::as_u64);
+        match (total, completed) {
+            (Some(total), Some(completed)) if total > 0 => {
+                let percent = completed as f64 / total as f64 * 100.0;
+                eprint!("\r[mover] {status}: {percent:5.1}% ({completed}/{total} bytes)");
+            }
+            _ => eprint!("\r[mover] {status}\x1b[K"),
+        }
+
+        if status == "success" {
+            eprintln!();
+        }
+    }
+
+    println!("[mover] pulled model {}", cli.model);
     Ok(())
 }
 
@@ -180,37 +424,85 @@ fn warm_model(cli: &Cli) -> Result<()> {
     }
 
     println!("[mover] warming model {} with a short prompt", cli.model);
+    let mut options = build_options(cli)?;
+    let defaults = options
+        .as_object_mut()
+        .expect("build_options always returns an object");
+    defaults
+        .entry("temperature")
+        .or_insert_with(|| serde_json::json!(0.0));
+    defaults
+        .entry("num_predict")
+        .or_insert_with(|| serde_json::json!(16));
+    generate(cli, &cli.warm_prompt, options)
+}
+
+/// Loads the model's weights into memory without generating tokens, by
+/// sending an empty prompt. Used by the watchdog after it restarts Ollama
+/// and for the periodic `keep_alive` refresh in `serve_only` mode; the
+/// startup load goes through `warm_model` instead.
+fn preload_model(cli: &Cli) -> Result<()> {
+    generate(cli, "", build_options(cli)?)
+}
+
+/// Builds the Ollama `options` object from `--num-ctx` and any
+/// `--option key=value` overrides.
+fn build_options(cli: &Cli) -> Result<Value> {
+    let mut options = serde_json::Map::new();
+    options.insert("num_ctx".to_string(), serde_json::json!(cli.num_ctx));
+
+    for raw in &cli.option {
+        let (key, value) = raw
+            .split_once('=')
+            .with_context(|| format!("`--option` value `{raw}` is not in `key=value` form"))?;
+        let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        options.insert(key.to_string(), value);
+    }
+
+    Ok(Value::Object(options))
+}
+
+/// Converts `--keep-alive` into the value Ollama expects: a bare integer
+/// (e.g. `-1` to never unload) must be a JSON number, not a numeric string,
+/// while duration strings like `5m` or `1h` are passed through as-is.
+fn keep_alive_value(raw: &str) -> Value {
+    match raw.parse::<i64>() {
+        Ok(seconds) => serde_json::json!(seconds),
+        Err(_) => Value::String(raw.to_string()),
+    }
+}
+
+fn generate(cli: &Cli, prompt: &str, options: Value) -> Result<()> {
     let url = format!("http://{}:{}/api/generate", cli.host, cli.port);
     let body = serde_json::json!({
         "model": cli.model,
-        "prompt": cli.warm_prompt,
+        "prompt": prompt,
         "stream": false,
-        "options": {
-            "temperature": 0.0,
-            "num_predict": 16,
-        }
+        "keep_alive": keep_alive_value(&cli.keep_alive),
+        "options": options,
     });
     let agent = build_agent(Duration::from_secs(5), Duration::from_secs(30));
-    let response = agent.post(&url).send_json(body);
+    let request = with_auth(agent.post(&url), cli.ollama_api_key.as_deref());
+    let response = request.send_json(body);
 
     match response {
         Ok(resp) => {
             if resp.status() >= 400 {
-                bail!("warm-up request failed with HTTP {}", resp.status());
+                bail!("generate request failed with HTTP {}", resp.status());
             }
             let value: Value = resp
                 .into_json()
-                .context("failed to decode warm-up response from Ollama")?;
+                .context("failed to decode generate response from Ollama")?;
             if let Some(error) = value.get("error") {
-                bail!("Ollama warm-up error: {error}");
+                bail!("Ollama generate error: {error}");
             }
             Ok(())
         }
         Err(ureq::Error::Status(code, resp)) => {
             let text = resp.into_string().unwrap_or_default();
-            bail!("warm-up request failed with HTTP {code}: {text}");
+            bail!("generate request failed with HTTP {code}: {text}");
         }
-        Err(err) => bail!("failed to send warm-up request to Ollama: {err}"),
+        Err(err) => bail!("failed to send generate request to Ollama: {err}"),
     }
 }
 
@@ -218,13 +510,15 @@ fn run_codex(cli: &Cli) -> Result<ExitStatus> {
     let base_url = format!("http://{}:{}/v1", cli.host, cli.port);
     let codex_path = resolve_codex_bin(&cli.codex_bin)?;
     println!("[mover] launching Codex via `{}`", codex_path.display());
+    let openai_api_key = cli.ollama_api_key.as_deref().unwrap_or(&cli.api_key);
     let mut command = Command::new(&codex_path);
     command
         .args(&cli.codex_args)
         .env("OPENAI_API_BASE", &base_url)
-        .env("OPENAI_API_KEY", &cli.api_key)
+        .env("OPENAI_API_KEY", openai_api_key)
         .env("OLLAMA_HOST", &cli.host)
         .env("OLLAMA_PORT", cli.port.to_string())
+        .env("OLLAMA_CONTEXT_LENGTH", cli.num_ctx.to_string())
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
@@ -274,6 +568,13 @@ fn format_exit_status(status: ExitStatus) -> String {
     }
 }
 
+fn with_auth(request: ureq::Request, api_key: Option<&str>) -> ureq::Request {
+    match api_key {
+        Some(key) => request.set("Authorization", &format!("Bearer {key}")),
+        None => request,
+    }
+}
+
 fn build_agent(connect: Duration, read: Duration) -> Agent {
     AgentBuilder::new()
         .timeout_connect(connect)
@@ -286,17 +587,43 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     let supervisor = OllamaSupervisor::ensure_running(&cli)?;
+
+    if cli.list_models {
+        let installed = fetch_installed_models(&cli)?;
+        if installed.is_empty() {
+            println!("[mover] no models installed");
+        } else {
+            println!("[mover] installed models:");
+            for name in installed {
+                println!("  {name}");
+            }
+        }
+        return Ok(());
+    }
+
     ensure_model_available(&cli)?;
     warm_model(&cli)?;
 
+    let supervisor = Arc::new(Mutex::new(supervisor));
+
     if cli.serve_only {
         println!("[mover] ollama is ready on {}:{}", cli.host, cli.port);
-        // Prevent the supervisor from being dropped immediately so the child keeps running.
-        std::mem::forget(supervisor);
+        println!(
+            "[mover] watchdog active, polling every {:?}",
+            WATCHDOG_POLL_INTERVAL
+        );
+        let watchdog = Watchdog::spawn(cli.clone(), supervisor, !cli.no_warmup);
+        // Blocks forever; the watchdog keeps Ollama running (and, unless
+        // `--no-warmup`, the model resident) across Codex invocations.
+        watchdog.join();
         return Ok(());
     }
 
-    let status = run_codex(&cli)?;
+    let watchdog = Watchdog::spawn(cli.clone(), Arc::clone(&supervisor), false);
+    let status = run_codex(&cli);
+    watchdog.stop();
+
+    let status = status?;
     if !status.success() {
         bail!("Codex exited with status {}", format_exit_status(status));
     }